@@ -1,31 +1,112 @@
 // src/ast.rs
 
+use crate::token::{Base, SimpleSpan};
+
 // Espressioni
-#[derive(Debug, Clone)]
-pub enum Expr {
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
     Identifier(String),
-    Literal(String), // Per '3b010', 'x', etc.
+    // Letterale numerico Verilog (`8'hFF`, `'d42`, `3'b01x`, ...), con
+    // larghezza e base conservate come dati strutturati invece che
+    // riappiattite in una stringa opaca, cosi codegen e pass futuri
+    // possono ragionare sulla larghezza invece di ri-parsare il testo.
+    Literal { width: Option<u32>, base: Base, digits: String },
     BinaryOp(Box<Expr>, Op, Box<Expr>),
+    // Segnaposto inserito dal recovery quando il contenuto di una coppia
+    // di parentesi bilanciate non parsa come espressione.
+    Error,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Op { Plus, Minus, BitAnd, BitOr }
 
+// Un'espressione porta con sé lo span del sorgente da cui è stata
+// ricavata, cosi Ariadne può puntare al nodo esatto invece che al token
+// grezzo più vicino.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: SimpleSpan,
+}
+
+// Range di un vettore, es. `[7:0]` o, in stile little-endian, `[0:7]`.
+// Conserva i bound così come dichiarati e la direzione, non solo la
+// larghezza risolta.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub left: u32,
+    pub right: u32,
+}
+
+impl Range {
+    pub fn width(&self) -> u32 {
+        if self.left >= self.right {
+            self.left - self.right + 1
+        } else {
+            self.right - self.left + 1
+        }
+    }
+
+    // `[0:7]` eccetera: il bit meno significativo è dichiarato per primo.
+    pub fn is_little_endian(&self) -> bool {
+        self.left < self.right
+    }
+}
+
 // Dichiarazioni all'interno di un modulo
 #[derive(Debug, Clone)]
-pub enum Declaration {
+pub enum DeclarationKind {
     Port {
         direction: PortDirection,
         is_reg: bool,
         name: String,
         width: u32,
+        range: Option<Range>,
+    },
+    // Rete interna dichiarata senza direzione, es. `wire [7:0] tmp;`.
+    Net {
+        is_reg: bool,
+        name: String,
+        width: u32,
+        range: Option<Range>,
     },
     Combinatorial(Vec<Statement>),
+    // `assign lhs = rhs;` a livello di modulo (continuous assign)
+    ContinuousAssign(Statement),
     // Blocco `gen if` che contiene altre dichiarazioni/statement
     ConditionalBlock {
         condition: String, // Condizione di generazione (es. "INCLUDE_LOGIC_OPS")
         declarations: Vec<Declaration>,
-    }
+    },
+    // Corpo di un blocco `generate ... endgenerate`: una sequenza di
+    // dichiarazioni, che possono a loro volta essere `ConditionalBlock`
+    // o `GenerateFor`.
+    Generate(Vec<Declaration>),
+    // Blocco `for (genvar ...)` dentro un `generate`.
+    GenerateFor(GenerateFor),
+    // Inserito dal recovery del parser quando una dichiarazione non può
+    // essere interpretata: permette di continuare a parsare il resto del
+    // modulo invece di abortire al primo errore.
+    Error,
+}
+
+// `for (genvar <genvar> = <init>; <genvar> < <limit>; <genvar> = <genvar> + <step>) begin ... end`
+// `limit` è tenuto come testo (letterale decimale o nome di parametro) e
+// viene risolto solo in fase di elaborazione, quando è noto il valore dei
+// parametri del modulo.
+#[derive(Debug, Clone)]
+pub struct GenerateFor {
+    pub genvar: String,
+    pub init: i64,
+    pub limit: String,
+    pub step: i64,
+    pub declarations: Vec<Declaration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub kind: DeclarationKind,
+    pub span: SimpleSpan,
 }
 
 #[derive(Debug, Clone)]
@@ -33,16 +114,31 @@ pub enum PortDirection { Input, Output }
 
 // Statement all'interno di un blocco (es. combinatorial)
 #[derive(Debug, PartialEq, Clone)]
-pub enum Statement {
+pub enum StatementKind {
     Module(String, Vec<Statement>),
     VarDecl(String),
-    Assignment(String, String), // Per ora, RHS è solo un identificatore
+    Assignment(String, Expr), // RHS è ora un albero di espressioni
 }
 
-// La radice del nostro AST
+#[derive(Debug, PartialEq, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: SimpleSpan,
+}
+
+// Un modulo dichiara il proprio nome e la lista delle porte (stile
+// non-ANSI: solo i nomi, direzione/larghezza arrivano dalle Declaration
+// corrispondenti nel corpo), seguiti dalle dichiarazioni fino a `endmodule`.
 #[derive(Debug)]
 pub struct Module {
     pub name: String,
+    pub ports: Vec<String>,
     pub declarations: Vec<Declaration>,
+    pub span: SimpleSpan,
 }
 
+// La radice del nostro AST: un file sorgente contiene più moduli.
+#[derive(Debug)]
+pub struct SourceFile {
+    pub modules: Vec<Module>,
+}
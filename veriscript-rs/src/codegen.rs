@@ -1,9 +1,40 @@
 // src/codegen.rs
 
-use crate::ast::Statement;
+use crate::ast::{Expr, ExprKind, Op, Statement, StatementKind};
+use crate::token::Base;
 use std::collections::HashMap;
 use std::fmt::Write;
 
+// Rende un'espressione come stringa Verilog-like, per l'output testuale.
+fn format_expr(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Identifier(name) => name.clone(),
+        ExprKind::Literal { width, base, digits } => {
+            let base_char = match base {
+                Base::Binary => 'b',
+                Base::Octal => 'o',
+                Base::Decimal => 'd',
+                Base::Hex => 'h',
+            };
+            match width {
+                Some(w) => format!("{}'{}{}", w, base_char, digits),
+                None if *base == Base::Decimal => digits.clone(),
+                None => format!("'{}{}", base_char, digits),
+            }
+        }
+        ExprKind::Error => "<error>".to_string(),
+        ExprKind::BinaryOp(lhs, op, rhs) => {
+            let op_str = match op {
+                Op::Plus => "+",
+                Op::Minus => "-",
+                Op::BitAnd => "&",
+                Op::BitOr => "|",
+            };
+            format!("{} {} {}", format_expr(lhs), op_str, format_expr(rhs))
+        }
+    }
+}
+
 pub struct CodeGenerator {
     output: String,
     indent_level: usize,
@@ -23,8 +54,8 @@ impl CodeGenerator {
     
     // MODIFICATA la firma della funzione
     fn visit_statement(&mut self, statement: &Statement, scope: &mut HashMap<String, String>) {
-        match statement {
-            Statement::Module(name, statements) => {
+        match &statement.kind {
+            StatementKind::Module(name, statements) => {
                 self.output.push_str(&format!("module {} {{\n", name));
                 self.indent_level += 1;
                 let mut inner_scope = HashMap::new(); // Crea un nuovo scope per il modulo
@@ -35,18 +66,18 @@ impl CodeGenerator {
                 self.indent_level -= 1;
                 self.output.push_str("}\n");
             }
-            Statement::VarDecl(name) => {
+            StatementKind::VarDecl(name) => {
                 // Esempio di utilizzo dello scope (può essere espanso)
                 scope.insert(name.clone(), "wire".to_string());
                 writeln!(self.output, "{}wire {};", self.get_indent(), name).unwrap();
             }
-            Statement::Assignment(lhs, rhs) => {
+            StatementKind::Assignment(lhs, rhs) => {
                 writeln!(
                     self.output,
                     "{}{} = {};",
                     self.get_indent(),
                     lhs,
-                    rhs
+                    format_expr(rhs)
                 )
                 .unwrap();
             }
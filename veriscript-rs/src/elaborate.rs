@@ -0,0 +1,209 @@
+// src/elaborate.rs
+//
+// Fase di elaborazione: risolve i blocchi `generate`/`genvar` di un
+// `Module` dato un insieme di valori di parametro, producendo un
+// `Module` con tutti i rami condizionali e i cicli `for` già srotolati
+// in dichiarazioni concrete.
+
+use std::collections::HashMap;
+
+use crate::ast::{Declaration, DeclarationKind, Expr, ExprKind, Module, Statement, StatementKind};
+
+pub type Params = HashMap<String, i64>;
+
+pub fn elaborate_module(module: &Module, params: &Params) -> Module {
+    Module {
+        name: module.name.clone(),
+        ports: module.ports.clone(),
+        declarations: elaborate_declarations(&module.declarations, params),
+        span: module.span,
+    }
+}
+
+fn elaborate_declarations(declarations: &[Declaration], params: &Params) -> Vec<Declaration> {
+    let mut out = Vec::new();
+    for decl in declarations {
+        elaborate_declaration(decl, params, &mut out);
+    }
+    out
+}
+
+fn elaborate_declaration(decl: &Declaration, params: &Params, out: &mut Vec<Declaration>) {
+    match &decl.kind {
+        DeclarationKind::Generate(body) => out.extend(elaborate_declarations(body, params)),
+
+        DeclarationKind::ConditionalBlock { condition, declarations } => {
+            // Un parametro non definito è falso di default.
+            if params.get(condition).copied().unwrap_or(0) != 0 {
+                out.extend(elaborate_declarations(declarations, params));
+            }
+        }
+
+        DeclarationKind::GenerateFor(gen_for) => {
+            // Uno step non positivo non farebbe mai avanzare `index`,
+            // trasformando questo loop in un loop infinito: lo segnaliamo e
+            // saltiamo lo srotolamento invece di bloccare l'elaborazione.
+            if gen_for.step < 1 {
+                eprintln!(
+                    "warning: generate for su '{}' ha uno step non positivo ({}), srotolamento saltato",
+                    gen_for.genvar, gen_for.step
+                );
+                return;
+            }
+
+            let limit = gen_for
+                .limit
+                .parse::<i64>()
+                .ok()
+                .or_else(|| params.get(&gen_for.limit).copied())
+                .unwrap_or(gen_for.init);
+
+            let mut index = gen_for.init;
+            while index < limit {
+                let mut iteration_params = params.clone();
+                iteration_params.insert(gen_for.genvar.clone(), index);
+                let unrolled = elaborate_declarations(&gen_for.declarations, &iteration_params);
+                out.extend(suffix_instance_names(unrolled, index));
+                index += gen_for.step;
+            }
+        }
+
+        _ => out.push(decl.clone()),
+    }
+}
+
+// Suffissa i nomi di porte/reti generate dentro un'iterazione di `for` con
+// l'indice del loop, cosi istanze successive non collidono, e propaga lo
+// stesso rinominamento a ogni riferimento a quei nomi negli statement
+// (`assign`/`combinatorial`) della stessa iterazione, non solo alla
+// dichiarazione che introduce il nome.
+fn suffix_instance_names(declarations: Vec<Declaration>, index: i64) -> Vec<Declaration> {
+    let mut renames = HashMap::new();
+    for decl in &declarations {
+        match &decl.kind {
+            DeclarationKind::Port { name, .. } | DeclarationKind::Net { name, .. } => {
+                renames.insert(name.clone(), format!("{}_{}", name, index));
+            }
+            _ => {}
+        }
+    }
+    declarations.into_iter().map(|decl| rename_declaration(decl, &renames)).collect()
+}
+
+fn rename_declaration(decl: Declaration, renames: &HashMap<String, String>) -> Declaration {
+    let kind = match decl.kind {
+        DeclarationKind::Port { direction, is_reg, name, width, range } => DeclarationKind::Port {
+            direction,
+            is_reg,
+            name: renames.get(&name).cloned().unwrap_or(name),
+            width,
+            range,
+        },
+        DeclarationKind::Net { is_reg, name, width, range } => DeclarationKind::Net {
+            is_reg,
+            name: renames.get(&name).cloned().unwrap_or(name),
+            width,
+            range,
+        },
+        DeclarationKind::ContinuousAssign(stmt) => {
+            DeclarationKind::ContinuousAssign(rename_statement(stmt, renames))
+        }
+        DeclarationKind::Combinatorial(stmts) => DeclarationKind::Combinatorial(
+            stmts.into_iter().map(|s| rename_statement(s, renames)).collect(),
+        ),
+        other => other,
+    };
+    Declaration { kind, span: decl.span }
+}
+
+fn rename_statement(stmt: Statement, renames: &HashMap<String, String>) -> Statement {
+    let kind = match stmt.kind {
+        StatementKind::Assignment(lhs, rhs) => StatementKind::Assignment(
+            renames.get(&lhs).cloned().unwrap_or(lhs),
+            rename_expr(rhs, renames),
+        ),
+        StatementKind::VarDecl(name) => {
+            StatementKind::VarDecl(renames.get(&name).cloned().unwrap_or(name))
+        }
+        StatementKind::Module(name, body) => StatementKind::Module(
+            name,
+            body.into_iter().map(|s| rename_statement(s, renames)).collect(),
+        ),
+    };
+    Statement { kind, span: stmt.span }
+}
+
+fn rename_expr(expr: Expr, renames: &HashMap<String, String>) -> Expr {
+    let kind = match expr.kind {
+        ExprKind::Identifier(name) => ExprKind::Identifier(renames.get(&name).cloned().unwrap_or(name)),
+        ExprKind::BinaryOp(lhs, op, rhs) => ExprKind::BinaryOp(
+            Box::new(rename_expr(*lhs, renames)),
+            op,
+            Box::new(rename_expr(*rhs, renames)),
+        ),
+        other @ (ExprKind::Literal { .. } | ExprKind::Error) => other,
+    };
+    Expr { kind, span: expr.span }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{module_parser, token_stream};
+    use crate::token::{lexer, SimpleSpan};
+    use chumsky::Parser;
+
+    fn parse_module(src: &str) -> Module {
+        let tokens = lexer().parse(src).into_output().expect("lexing should succeed");
+        let eoi = SimpleSpan::new(src.len(), src.len());
+        let stream = token_stream(&tokens, eoi);
+        let result = module_parser().parse(stream).into_output().expect("parsing should succeed");
+        result
+    }
+
+    // Non solo il `wire tmp;` deve diventare `tmp_0`/`tmp_1`: anche i
+    // riferimenti a `tmp` dentro `assign` nella stessa iterazione devono
+    // seguire lo stesso rinominamento, altrimenti restano agganciati a un
+    // nome che non esiste più dopo lo srotolamento.
+    #[test]
+    fn generate_for_renames_identifiers_in_assign_statements_too() {
+        let module = parse_module(
+            "module m(a); generate for (genvar i = 0; i < 2; i = i + 1) begin wire tmp; assign tmp = a; end endgenerate endmodule",
+        );
+        let elaborated = elaborate_module(&module, &Params::new());
+
+        let net_names: Vec<_> = elaborated
+            .declarations
+            .iter()
+            .filter_map(|d| match &d.kind {
+                DeclarationKind::Net { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(net_names, vec!["tmp_0", "tmp_1"]);
+
+        let assign_lhs: Vec<_> = elaborated
+            .declarations
+            .iter()
+            .filter_map(|d| match &d.kind {
+                DeclarationKind::ContinuousAssign(stmt) => match &stmt.kind {
+                    StatementKind::Assignment(lhs, _) => Some(lhs.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        assert_eq!(assign_lhs, vec!["tmp_0", "tmp_1"]);
+    }
+
+    // Uno step non positivo non deve mai far girare il loop di
+    // srotolamento all'infinito: l'iterazione va saltata.
+    #[test]
+    fn generate_for_with_non_positive_step_does_not_hang() {
+        let module = parse_module(
+            "module m(a); generate for (genvar i = 0; i < 2; i = i + 0) begin wire tmp; end endgenerate endmodule",
+        );
+        let elaborated = elaborate_module(&module, &Params::new());
+        assert!(elaborated.declarations.is_empty());
+    }
+}
@@ -7,28 +7,33 @@ use ariadne::{Color, Fmt, Label, Report, ReportKind, Source};
 
 mod ast;
 mod codegen;
+mod elaborate;
 mod parser;
 mod token;
 
-use crate::parser::module_parser;
-use crate::token::{lexer, SimpleSpan, VToken};
+use crate::ast::{Declaration, DeclarationKind, SourceFile};
+use crate::elaborate::{elaborate_module, Params};
+use crate::parser::{source_file_parser, token_stream};
+use crate::token::{lexer, SimpleSpan};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let path = env::args().nth(1).expect("Usage: veridec <path>");
     let src = fs::read_to_string(&path)?;
 
     // --- 1. LEXER ---
-    let (tokens, lex_errs) = lexer().parse_recovery(&src);
+    let (tokens, lex_errs) = lexer().parse(&src).into_output_errors();
 
     // --- 2. PARSER ---
-    let (ast, parse_errs) = if let Some(tokens) = tokens {
-        // ---- LA CORREZIONE FONDAMENTALE È QUI ----
-        // Trasformiamo il `Vec<(VToken, SimpleSpan)>` in uno `Stream`
-        // che Chumsky può usare. Questo risolve TUTTI gli errori.
-        let stream = chumsky::Stream::from_iter(tokens.into_iter())
-            .spanned(SimpleSpan::new(src.len(), src.len()));
-        
-        module_parser().parse_recovery(stream)
+    // I parser in `parser.rs` vogliono uno stream il cui `Token` sia
+    // `VToken` nudo (non la coppia `(VToken, SimpleSpan)` emessa dal
+    // lexer) e il cui `Span` sia lo `SimpleSpan` originale per ogni
+    // token, non un intervallo di indici di token: `token_stream`
+    // costruisce quell'input via `Input::map`.
+    let (ast, parse_errs) = if let Some(tokens) = &tokens {
+        let eoi = SimpleSpan::new(src.len(), src.len());
+        source_file_parser()
+            .parse(token_stream(tokens, eoi))
+            .into_output_errors()
     } else {
         (None, Vec::new())
     };
@@ -43,19 +48,79 @@ fn main() -> Result<(), Box<dyn Error>> {
             .print((&path, Source::from(&src)))?;
     }
 
-    // Stampa errori del parser (basati su token)
+    // Stampa errori del parser: lo span di `Rich` è già preciso sul punto
+    // di fallimento, ma per i nodi prodotti dal recovery (`Declaration::Error`)
+    // aggiungiamo un'etichetta ancorata allo span dell'esatto nodo AST
+    // scartato, non al token grezzo più vicino.
+    //
+    // `VToken` non implementa `Display` (solo `Debug`), quindi `Rich`
+    // stesso non lo implementa. Usiamo `{:?}` invece di
+    // `.to_string()`/`.reason().to_string()`.
     for e in parse_errs {
         Report::build(ReportKind::Error, &path, e.span().start)
-            .with_message(e.to_string())
-            .with_label(Label::new((&path, e.span().into_range())).with_message(e.reason().to_string()).with_color(Color::Red))
+            .with_message(format!("{:?}", e))
+            .with_label(Label::new((&path, e.span().into_range())).with_message(format!("{:?}", e.reason())).with_color(Color::Red))
             .finish()
             .print((&path, Source::from(&src)))?;
     }
-    
+
+    if let Some(ast) = &ast {
+        report_recovered_declarations(&path, &src, ast)?;
+    }
+
     // --- 4. SUCCESSO ---
     if let Some(ast) = ast {
         println!("AST generato con successo:\n{:#?}", ast);
+
+        // --- 5. ELABORAZIONE ---
+        // Srotola i blocchi `generate`/`genvar` di ogni modulo. Il binario
+        // non espone ancora un modo per passare valori di parametro da
+        // riga di comando, quindi si elabora con l'insieme vuoto: i `gen
+        // if` risultano tutti falsi e i `for` il cui bound è un nome di
+        // parametro (anziché un letterale) non producono alcuna iterazione.
+        let params = Params::new();
+        let elaborated: Vec<_> = ast.modules.iter().map(|module| elaborate_module(module, &params)).collect();
+        println!("\nModuli elaborati (generate/genvar risolti):\n{:#?}", elaborated);
+    }
+
+    Ok(())
+}
+
+// Segnala ogni `Declaration::Error` rimasto nell'AST dopo il recovery,
+// puntando Ariadne esattamente allo span del nodo scartato.
+fn report_recovered_declarations(path: &str, src: &str, ast: &SourceFile) -> Result<(), Box<dyn Error>> {
+    for module in &ast.modules {
+        for decl in &module.declarations {
+            report_recovered_declaration(path, src, decl)?;
+        }
     }
+    Ok(())
+}
 
+fn report_recovered_declaration(path: &str, src: &str, decl: &Declaration) -> Result<(), Box<dyn Error>> {
+    if let DeclarationKind::Error = decl.kind {
+        Report::build(ReportKind::Warning, path, decl.span.start)
+            .with_message("declaration skipped during error recovery")
+            .with_label(
+                Label::new((path, decl.span.into_range()))
+                    .with_message("could not parse this declaration".fg(Color::Yellow))
+                    .with_color(Color::Yellow),
+            )
+            .finish()
+            .print((path, Source::from(src)))?;
+    }
+    match &decl.kind {
+        DeclarationKind::ConditionalBlock { declarations, .. } | DeclarationKind::Generate(declarations) => {
+            for inner in declarations {
+                report_recovered_declaration(path, src, inner)?;
+            }
+        }
+        DeclarationKind::GenerateFor(gen_for) => {
+            for inner in &gen_for.declarations {
+                report_recovered_declaration(path, src, inner)?;
+            }
+        }
+        _ => {}
+    }
     Ok(())
-}
\ No newline at end of file
+}
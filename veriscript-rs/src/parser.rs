@@ -1,60 +1,415 @@
+use chumsky::input::ValueInput;
 use chumsky::prelude::*;
 use chumsky::recursive::recursive;
 
 use crate::ast::*;
-use crate::token::{SimpleSpan, VToken};
+use crate::token::{Base, SimpleSpan, VToken};
 
-type TokenStream<'a> = &'a [(VToken, SimpleSpan)];
-type ParserError<'a> = extra::Err<Rich<'a, (VToken, SimpleSpan)>>;
+type ParserError<'a> = extra::Err<Rich<'a, VToken, SimpleSpan>>;
 
-// Parser ausiliario per un token specifico.
-fn just<'a>(token: VToken) -> impl Parser<'a, TokenStream<'a>, (VToken, SimpleSpan), ParserError<'a>> + Clone {
-    any().filter(move |(t, _)| *t == token)
+// Parser ausiliario per un token specifico. Generico sull'input, cosi
+// funziona sia sullo stream grezzo `&[(VToken, SimpleSpan)]` (via
+// `.map(...)`, vedi `main.rs` e i test in fondo a questo file) sia su
+// qualunque altro input che esponga `Token = VToken` e `Span = SimpleSpan`.
+fn just<'a, I>(token: VToken) -> impl Parser<'a, I, VToken, ParserError<'a>> + Clone
+where
+    I: ValueInput<'a, Token = VToken, Span = SimpleSpan>,
+{
+    any().filter(move |t: &VToken| *t == token)
 }
 
 // Parser ausiliario per un identificatore.
-fn ident<'a>() -> impl Parser<'a, TokenStream<'a>, String, ParserError<'a>> + Clone {
-    any().try_map(|(token, span)| match token {
+fn ident<'a, I>() -> impl Parser<'a, I, String, ParserError<'a>> + Clone
+where
+    I: ValueInput<'a, Token = VToken, Span = SimpleSpan>,
+{
+    any().try_map(|token, span| match token {
         VToken::Ident(s) => Ok(s),
         _ => Err(Rich::custom(span, "Expected identifier")),
     })
 }
 
 // Parser ausiliario per una direzione di porta.
-fn port_direction<'a>() -> impl Parser<'a, TokenStream<'a>, PortDirection, ParserError<'a>> + Clone {
-    any().try_map(|(token, span)| match token {
+fn port_direction<'a, I>() -> impl Parser<'a, I, PortDirection, ParserError<'a>> + Clone
+where
+    I: ValueInput<'a, Token = VToken, Span = SimpleSpan>,
+{
+    any().try_map(|token, span| match token {
         VToken::Input => Ok(PortDirection::Input),
         VToken::Output => Ok(PortDirection::Output),
         _ => Err(Rich::custom(span, "Expected 'input' or 'output'")),
     })
 }
 
-pub fn module_parser<'a>() -> impl Parser<'a, TokenStream<'a>, Module, ParserError<'a>> {
-    let declaration = recursive(|_declaration| {
-        port_direction()
+// Parser di espressioni a precedenza operatore (precedence climbing).
+// I livelli sono annidati dal legame più debole al più forte: `|`/`&` hanno
+// binding power più bassa di `+`/`-`, quindi vengono piegati (fold) per
+// ultimi e finiscono più in alto nell'albero, cioè si legano più lontano.
+// Ogni nodo prodotto porta con sé lo span esatto del sorgente da cui
+// proviene (via `map_with`), non solo l'intervallo di token.
+pub fn expr_parser<'a, I>() -> impl Parser<'a, I, Expr, ParserError<'a>> + Clone
+where
+    I: ValueInput<'a, Token = VToken, Span = SimpleSpan>,
+{
+    recursive(|expr| {
+        let number = any().try_map(|token, span| match token {
+            VToken::Number { width, base, digits } => Ok(ExprKind::Literal { width, base, digits }),
+            _ => Err(Rich::custom(span, "Expected number")),
+        });
+
+        // Con `Input::Token = VToken`, `nested_delimiters` riceve finalmente
+        // il tipo che si aspetta (un `VToken` nudo, non la coppia
+        // `(VToken, SimpleSpan)`): quando il contenuto fra parentesi non
+        // parsa come espressione, scarta i token fino alla parentesi di
+        // chiusura corrispondente e sintetizza un `ExprKind::Error` invece
+        // di far abortire l'intero parsing.
+        let parenthesized = expr
+            .clone()
+            .delimited_by(just(VToken::LParen), just(VToken::RParen))
+            .recover_with(via_parser(nested_delimiters(
+                VToken::LParen,
+                VToken::RParen,
+                [],
+                |span| Expr {
+                    kind: ExprKind::Error,
+                    span,
+                },
+            )));
+
+        let primary = choice((
+            ident().map(ExprKind::Identifier).map_with(|kind, e| Expr { kind, span: e.span() }),
+            number.map_with(|kind, e| Expr { kind, span: e.span() }),
+            parenthesized,
+        ));
+
+        // `+`/`-`: binding power più alta, left-associative.
+        let additive = primary.clone().foldl_with(
+            choice((
+                just(VToken::Plus).to(Op::Plus),
+                just(VToken::Minus).to(Op::Minus),
+            ))
+            .then(primary)
+            .repeated(),
+            |lhs, (op, rhs), e| Expr {
+                kind: ExprKind::BinaryOp(Box::new(lhs), op, Box::new(rhs)),
+                span: e.span(),
+            },
+        );
+
+        // `&`/`|`: binding power più bassa di `+`/`-`.
+        additive.clone().foldl_with(
+            choice((
+                just(VToken::Amp).to(Op::BitAnd),
+                just(VToken::Pipe).to(Op::BitOr),
+            ))
+            .then(additive)
+            .repeated(),
+            |lhs, (op, rhs), e| Expr {
+                kind: ExprKind::BinaryOp(Box::new(lhs), op, Box::new(rhs)),
+                span: e.span(),
+            },
+        )
+    })
+}
+
+// Parser per un range di vettore `[ <msb> : <lsb> ]`. I bound sono
+// interi decimali semplici; l'ordine (big- o little-endian) viene
+// preservato così com'è scritto nel sorgente.
+fn range_parser<'a, I>() -> impl Parser<'a, I, Range, ParserError<'a>> + Clone
+where
+    I: ValueInput<'a, Token = VToken, Span = SimpleSpan>,
+{
+    let bound = any().try_map(|token, span| match token {
+        VToken::Number { width: None, base: Base::Decimal, digits } => digits
+            .parse::<u32>()
+            .map_err(|_| Rich::custom(span, "Invalid range bound")),
+        _ => Err(Rich::custom(span, "Expected a plain decimal number in range")),
+    });
+
+    bound
+        .clone()
+        .then_ignore(just(VToken::Colon))
+        .then(bound)
+        .delimited_by(just(VToken::LBracket), just(VToken::RBracket))
+        .map(|(left, right)| Range { left, right })
+}
+
+// Parser per un intero decimale semplice (usato per i bound di un `for`
+// di generazione).
+fn int_literal<'a, I>() -> impl Parser<'a, I, i64, ParserError<'a>> + Clone
+where
+    I: ValueInput<'a, Token = VToken, Span = SimpleSpan>,
+{
+    any().try_map(|token, span| match token {
+        VToken::Number { width: None, base: Base::Decimal, digits } => digits
+            .parse::<i64>()
+            .map_err(|_| Rich::custom(span, "Invalid integer literal")),
+        _ => Err(Rich::custom(span, "Expected a plain decimal number")),
+    })
+}
+
+pub fn module_parser<'a, I>() -> impl Parser<'a, I, Module, ParserError<'a>>
+where
+    I: ValueInput<'a, Token = VToken, Span = SimpleSpan>,
+{
+    let declaration = recursive(|declaration| {
+        // `if (<condition>) begin ... end` — `<condition>` è il nome di un
+        // parametro, risolto (truthy/falsy) in fase di elaborazione.
+        let gen_if = just(VToken::If)
+            .ignore_then(ident().delimited_by(just(VToken::LParen), just(VToken::RParen)))
+            .then_ignore(just(VToken::Begin))
+            .then(declaration.clone().repeated().collect::<Vec<_>>())
+            .then_ignore(just(VToken::End))
+            .map(|(condition, declarations)| DeclarationKind::ConditionalBlock {
+                condition,
+                declarations,
+            });
+
+        // Limite del ciclo: un letterale decimale o il nome di un
+        // parametro, risolto solo in fase di elaborazione.
+        let for_limit = choice((ident(), int_literal().map(|n| n.to_string())));
+
+        // `for (genvar <i> = <init>; <i> < <limit>; <i> = <i> + <step>) begin ... end`
+        // La sintassi è volutamente ristretta alla forma canonica del
+        // generate-for di Verilog-2001, che è l'unica che serve per
+        // srotolare il loop in fase di elaborazione.
+        let gen_for = just(VToken::For)
+            .ignore_then(just(VToken::LParen))
+            .ignore_then(just(VToken::Genvar).or_not())
+            .ignore_then(ident())
+            .then_ignore(just(VToken::AssignEq))
+            .then(int_literal())
+            .then_ignore(just(VToken::Semicolon))
+            .then_ignore(ident()) // ripete il nome del genvar nella condizione
+            .then_ignore(just(VToken::Lt))
+            .then(for_limit)
+            .then_ignore(just(VToken::Semicolon))
+            .then_ignore(ident()) // ripete il nome del genvar nell'incremento
+            .then_ignore(just(VToken::AssignEq))
+            .then_ignore(ident()) // `i = i + step`
+            .then_ignore(just(VToken::Plus))
+            .then(int_literal())
+            .then_ignore(just(VToken::RParen))
+            .then_ignore(just(VToken::Begin))
+            .then(declaration.clone().repeated().collect::<Vec<_>>())
+            .then_ignore(just(VToken::End))
+            .map(|((((genvar, init), limit), step), declarations)| {
+                DeclarationKind::GenerateFor(GenerateFor {
+                    genvar,
+                    init,
+                    limit,
+                    step,
+                    declarations,
+                })
+            });
+
+        // `generate ... endgenerate`: corpo fatto di `gen_if`/`gen_for`,
+        // ognuno già trattato come una `declaration` a pieno titolo (quindi
+        // con span e recovery) tramite il parser ricorsivo.
+        let generate = just(VToken::Generate)
+            .ignore_then(declaration.clone().repeated().collect::<Vec<_>>())
+            .then_ignore(just(VToken::EndGenerate))
+            .map(DeclarationKind::Generate);
+
+        let port_decl = port_direction()
             .then(just(VToken::Reg).or_not())
+            .then(range_parser().or_not())
             .then(ident())
             .then_ignore(just(VToken::Semicolon))
-            .map(|((direction, is_reg), name)| {
-                Declaration::Port(Port {
+            .map(|(((direction, is_reg), range), name)| {
+                let width = range.as_ref().map(Range::width).unwrap_or(1);
+                DeclarationKind::Port {
                     direction,
                     is_reg: is_reg.is_some(),
                     name,
-                })
-            })
+                    width,
+                    range,
+                }
+            });
+
+        // Rete interna `wire`/`reg` dichiarata senza direzione.
+        let net_decl = choice((just(VToken::Wire).to(false), just(VToken::Reg).to(true)))
+            .then(range_parser().or_not())
+            .then(ident())
+            .then_ignore(just(VToken::Semicolon))
+            .map(|((is_reg, range), name)| {
+                let width = range.as_ref().map(Range::width).unwrap_or(1);
+                DeclarationKind::Net { is_reg, name, width, range }
+            });
+
+        // `assign <lhs> = <expr>;` — continuous assign a livello di modulo.
+        let continuous_assign = just(VToken::Assign)
+            .ignore_then(ident())
+            .then_ignore(just(VToken::AssignEq))
+            .then(expr_parser())
+            .then_ignore(just(VToken::Semicolon))
+            .map_with(|(name, rhs), e| {
+                let stmt = Statement {
+                    kind: StatementKind::Assignment(name, rhs),
+                    span: e.span(),
+                };
+                DeclarationKind::ContinuousAssign(stmt)
+            });
+
+        let not_a_recovery_boundary = just(VToken::Semicolon)
+            .or(just(VToken::End))
+            .or(just(VToken::EndModule))
+            .or(just(VToken::EndGenerate))
+            .not();
+
+        // Il recovery deve garantire di consumare almeno un token, altrimenti
+        // `declaration.repeated()` lo richiama all'infinito nella stessa
+        // posizione (successo a larghezza zero = loop infinito). Scartiamo
+        // quindi o almeno un token di "spazzatura" prima del prossimo `;`,
+        // `end`/`endmodule`/`endgenerate`, oppure, se siamo già fermi su un
+        // `;` solitario, consumiamo solo quello. Se siamo già fermi su uno
+        // dei boundary di blocco (`end`/`endmodule`/`endgenerate`) nessuno
+        // dei due rami matcha e il recovery fallisce, lasciando propagare
+        // l'errore originale invece di sintetizzare un `Declaration::Error`
+        // a larghezza zero.
+        let skip_garbage = any()
+            .and_is(not_a_recovery_boundary)
+            .repeated()
+            .at_least(1)
+            .then(just(VToken::Semicolon).or_not());
+        let skip_lone_semicolon = just(VToken::Semicolon).ignored();
+
+        choice((continuous_assign, port_decl, net_decl, gen_if, gen_for, generate))
+            .map_with(|kind, e| Declaration { kind, span: e.span() })
+            // In caso di errore, scarta i token fino al prossimo `;`
+            // (consumandolo) o fino a `end`/`endmodule`/`endgenerate`
+            // (senza consumarli) e prosegue, cosi un
+            // singolo statement malformato non fa abortire l'intero modulo.
+            .recover_with(via_parser(
+                skip_garbage
+                    .ignored()
+                    .or(skip_lone_semicolon)
+                    .map_with(|_, e| Declaration { kind: DeclarationKind::Error, span: e.span() }),
+            ))
     });
 
+    // Lista porte del modulo: `( a, b, c )`, nomi separati da virgola.
+    let port_list = ident()
+        .separated_by(just(VToken::Comma))
+        .allow_trailing()
+        .collect::<Vec<_>>()
+        .delimited_by(just(VToken::LParen), just(VToken::RParen));
+
     just(VToken::Module)
         .ignore_then(ident())
-        .then(
-            declaration
-                .repeated()
-                .collect::<Vec<_>>()
-                .delimited_by(just(VToken::LParen), just(VToken::RParen)),
-        )
+        .then(port_list)
         .then_ignore(just(VToken::Semicolon))
-        // Ho rimosso la strategia di recovery errata, come suggerito dal compilatore
+        .then(declaration.repeated().collect::<Vec<_>>())
+        .then_ignore(just(VToken::EndModule))
+        .map_with(|((name, ports), declarations), e| Module {
+            name,
+            ports,
+            declarations,
+            span: e.span(),
+        })
+}
+
+// Radice del parser: un file può contenere più moduli in sequenza.
+pub fn source_file_parser<'a, I>() -> impl Parser<'a, I, SourceFile, ParserError<'a>>
+where
+    I: ValueInput<'a, Token = VToken, Span = SimpleSpan>,
+{
+    module_parser()
+        .repeated()
+        .collect::<Vec<_>>()
         .then_ignore(end())
-        .map(|(name, body)| Module { name, body })
+        .map(|modules| SourceFile { modules })
 }
 
+// Costruisce, a partire dallo stream grezzo prodotto dal lexer
+// (`&[(VToken, SimpleSpan)]`), l'input atteso dai parser di questo modulo:
+// uno stream il cui `Token` è `VToken` nudo e il cui `Span` è lo
+// `SimpleSpan` originale del lexer (non un intervallo di indici di
+// token). Usato sia da `main.rs` sia dagli helper di test qui sotto e in
+// `elaborate.rs`, cosi la costruzione dello stream non è duplicata.
+pub fn token_stream<'a>(
+    tokens: &'a [(VToken, SimpleSpan)],
+    eoi: SimpleSpan,
+) -> impl ValueInput<'a, Token = VToken, Span = SimpleSpan> {
+    tokens.map(eoi, |(t, s)| (t, s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::lexer;
+
+    fn parse_source(src: &str) -> SourceFile {
+        let tokens = lexer().parse(src).into_output().expect("lexing should succeed");
+        let eoi = SimpleSpan::new(src.len(), src.len());
+        let stream = token_stream(&tokens, eoi);
+        let result = source_file_parser().parse(stream).into_output().expect("parsing should succeed");
+        result
+    }
+
+    // Regressione per il loop infinito in `declaration.repeated()`: il
+    // recovery precedente poteva avere successo senza consumare token
+    // quando non c'era niente da scartare, facendo girare il parser
+    // all'infinito perfino su un modulo valido. Se questo test non
+    // termina, il bug è tornato.
+    #[test]
+    fn parses_a_minimal_module_without_hanging() {
+        let ast = parse_source("module m(a); input a; endmodule");
+        assert_eq!(ast.modules.len(), 1);
+        assert_eq!(ast.modules[0].name, "m");
+        assert_eq!(ast.modules[0].declarations.len(), 1);
+    }
+
+    // Una dichiarazione malformata seguita da una valida deve comunque terminare:
+    // il recovery scarta la dichiarazione rotta e il parsing prosegue.
+    #[test]
+    fn recovers_from_a_malformed_declaration_without_hanging() {
+        let ast = parse_source("module m(a); bogus tokens here; input a; endmodule");
+        assert_eq!(ast.modules.len(), 1);
+        assert_eq!(ast.modules[0].declarations.len(), 2);
+        assert!(matches!(ast.modules[0].declarations[0].kind, DeclarationKind::Error));
+    }
+
+    // Un letterale sized/based come `8'hFF` deve arrivare all'AST con
+    // larghezza e base come dati strutturati, non ri-appiattito in una
+    // stringa opaca: altrimenti codegen e pass futuri non possono
+    // ragionare sulla larghezza senza ri-parsare il testo.
+    #[test]
+    fn sized_number_literal_keeps_structured_width_and_base() {
+        let ast = parse_source("module m(a); assign a = 8'hFF; endmodule");
+        let decl = &ast.modules[0].declarations[0];
+        let DeclarationKind::ContinuousAssign(stmt) = &decl.kind else {
+            panic!("expected a ContinuousAssign declaration");
+        };
+        let StatementKind::Assignment(_, rhs) = &stmt.kind else {
+            panic!("expected an Assignment statement");
+        };
+        match &rhs.kind {
+            ExprKind::Literal { width, base, digits } => {
+                assert_eq!(*width, Some(8));
+                assert_eq!(*base, Base::Hex);
+                assert_eq!(digits, "FF");
+            }
+            other => panic!("expected a structured Literal, got {:?}", other),
+        }
+    }
+
+    // Lo span di un nodo AST deve essere un vero offset in byte nel
+    // sorgente, non un intervallo di indici di token: altrimenti Ariadne
+    // punta le diagnostiche nel punto sbagliato su qualunque file più
+    // lungo di un paio di righe.
+    #[test]
+    fn expression_span_is_a_byte_offset_not_a_token_index() {
+        let ast = parse_source("module m(a); assign a = (a); endmodule");
+        let decl = &ast.modules[0].declarations[0];
+        let DeclarationKind::ContinuousAssign(stmt) = &decl.kind else {
+            panic!("expected a ContinuousAssign declaration");
+        };
+        let StatementKind::Assignment(_, rhs) = &stmt.kind else {
+            panic!("expected an Assignment statement");
+        };
+        // `a` dentro le parentesi interne inizia al byte 25 di questo
+        // sorgente, ben oltre quanto un conteggio di token produrrebbe.
+        assert_eq!(rhs.span.into_range(), 25..26);
+    }
+}